@@ -2,7 +2,7 @@ pub mod vulkan;
 use buffer::BufferApi;
 use shader::ShaderApi;
 //use renderpass::RenderpassApi;
-//use pipeline::PipelineApi;
+use pipeline::PipelineApi;
 use descriptor::{DescriptorApi, LayoutApi};
 use image::ImageApi;
 use render::RenderApi;
@@ -22,4 +22,5 @@ where
     type Swapchain: SwapchainApi;
     type Descriptor;
     type Layout: LayoutApi;
+    type Pipeline: PipelineApi;
 }
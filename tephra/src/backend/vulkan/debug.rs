@@ -0,0 +1,111 @@
+use ash::extensions::DebugUtils;
+use ash::version::{EntryV1_0, InstanceV1_0};
+use ash::vk;
+use log::{debug, error, trace, warn};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
+use std::ops::Drop;
+use std::ptr;
+
+/// Name of the validation layer the opt-in validation subsystem pulls in.
+pub const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Whether validation should be enabled, given an explicit flag. The
+/// `TEPHRA_VALIDATION` environment variable forces it on regardless of the
+/// flag, which is handy for debugging a release build without recompiling.
+pub fn validation_enabled(flag: bool) -> bool {
+    flag || std::env::var_os("TEPHRA_VALIDATION").is_some()
+}
+
+/// Owns the `VK_EXT_debug_utils` messenger. Registered during instance
+/// creation when validation is enabled and destroyed on drop.
+pub struct DebugMessenger {
+    debug_utils: DebugUtils,
+    messenger: vk::DebugUtilsMessengerEXT,
+}
+
+impl DebugMessenger {
+    pub fn new<E: EntryV1_0, I: InstanceV1_0>(entry: &E, instance: &I) -> Self {
+        let debug_utils = DebugUtils::new(entry, instance);
+        let create_info = vk::DebugUtilsMessengerCreateInfoEXT {
+            s_type: vk::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            message_severity: vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
+                | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
+                | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
+                | vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE,
+            message_type: vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+            pfn_user_callback: Some(vulkan_debug_callback),
+            p_user_data: ptr::null_mut(),
+        };
+        let messenger = unsafe {
+            debug_utils
+                .create_debug_utils_messenger_ext(&create_info, None)
+                .expect("Unable to create debug utils messenger")
+        };
+        DebugMessenger {
+            debug_utils,
+            messenger,
+        }
+    }
+
+    /// Attach a human-readable name to a Vulkan object so it shows up in
+    /// validation output and RenderDoc. `object_handle` is the raw `u64` handle
+    /// behind one of the crate's `new_typed_handle!` handles.
+    pub fn set_debug_name(
+        &self,
+        device: vk::Device,
+        object_type: vk::ObjectType,
+        object_handle: u64,
+        name: &str,
+    ) {
+        let c_name = CString::new(name).expect("Debug name contained a nul byte");
+        let name_info = vk::DebugUtilsObjectNameInfoEXT {
+            s_type: vk::StructureType::DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+            p_next: ptr::null(),
+            object_type,
+            object_handle,
+            p_object_name: c_name.as_ptr(),
+        };
+        unsafe {
+            self.debug_utils
+                .debug_utils_set_object_name_ext(device, &name_info)
+                .expect("Unable to set debug name");
+        }
+    }
+}
+
+impl Drop for DebugMessenger {
+    fn drop(&mut self) {
+        unsafe {
+            self.debug_utils
+                .destroy_debug_utils_messenger_ext(self.messenger, None);
+        }
+    }
+}
+
+unsafe extern "system" fn vulkan_debug_callback(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _p_user_data: *mut c_void,
+) -> vk::Bool32 {
+    let message = CStr::from_ptr((*p_callback_data).p_message).to_string_lossy();
+    let ty = match message_type {
+        vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => "general",
+        vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION => "validation",
+        vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => "performance",
+        _ => "unknown",
+    };
+    match message_severity {
+        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => error!("[{}] {}", ty, message),
+        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => warn!("[{}] {}", ty, message),
+        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => debug!("[{}] {}", ty, message),
+        _ => trace!("[{}] {}", ty, message),
+    }
+    // The spec mandates that the application always returns `VK_FALSE` here.
+    vk::FALSE
+}
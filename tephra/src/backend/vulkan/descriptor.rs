@@ -0,0 +1,268 @@
+use super::Context;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use commandbuffer::{Descriptor, ShaderView};
+use descriptor::{
+    Binding, CreateLayout, CreatePool, DescriptorApi, DescriptorHandle, DescriptorResource,
+    DescriptorSizes, DescriptorType, LayoutApi, NativeLayout, NativePool, PoolApi, ShaderStage,
+};
+use downcast::Downcast;
+use std::ptr;
+
+fn to_vk_stage_flags(stage: ShaderStage) -> vk::ShaderStageFlags {
+    match stage {
+        ShaderStage::Vertex => vk::ShaderStageFlags::VERTEX,
+        ShaderStage::Fragment => vk::ShaderStageFlags::FRAGMENT,
+        ShaderStage::Compute => vk::ShaderStageFlags::COMPUTE,
+        ShaderStage::AllGraphics => vk::ShaderStageFlags::ALL_GRAPHICS,
+        ShaderStage::All => vk::ShaderStageFlags::ALL,
+    }
+}
+
+pub(crate) fn to_vk_descriptor_type(ty: DescriptorType) -> vk::DescriptorType {
+    match ty {
+        DescriptorType::Uniform => vk::DescriptorType::UNIFORM_BUFFER,
+        DescriptorType::Storage => vk::DescriptorType::STORAGE_BUFFER,
+        DescriptorType::SampledImage => vk::DescriptorType::SAMPLED_IMAGE,
+        DescriptorType::CombinedImageSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+    }
+}
+
+impl CreatePool for Context {
+    fn create_pool(
+        &self,
+        alloc_size: u32,
+        data: &[ShaderView],
+        sizes: DescriptorSizes,
+    ) -> NativePool {
+        // One pool size per non-empty descriptor class. `SampledImage` and
+        // `CombinedImageSampler` are distinct Vulkan types, so each gets its
+        // own pool size sized from the matching `DescriptorSizes` count.
+        let mut pool_sizes = Vec::new();
+        if sizes.buffer > 0 {
+            pool_sizes.push(vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: sizes.buffer * alloc_size,
+            });
+        }
+        if sizes.storage > 0 {
+            pool_sizes.push(vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: sizes.storage * alloc_size,
+            });
+        }
+        if sizes.sampled_images > 0 {
+            pool_sizes.push(vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::SAMPLED_IMAGE,
+                descriptor_count: sizes.sampled_images * alloc_size,
+            });
+        }
+        if sizes.combined_image_samplers > 0 {
+            pool_sizes.push(vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: sizes.combined_image_samplers * alloc_size,
+            });
+        }
+        let pool_info = vk::DescriptorPoolCreateInfo {
+            s_type: vk::StructureType::DESCRIPTOR_POOL_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            max_sets: alloc_size,
+            pool_size_count: pool_sizes.len() as u32,
+            p_pool_sizes: pool_sizes.as_ptr(),
+        };
+        let pool = unsafe {
+            self.device
+                .create_descriptor_pool(&pool_info, None)
+                .expect("Unable to create descriptor pool")
+        };
+        // Route through `CreateLayout` so the layout sets are allocated
+        // against is built by the exact same code, with the same
+        // `stageFlags` policy, as the layout baked into a pipeline that binds
+        // them — see `pipeline::set_layout` in the Vulkan pipeline builder.
+        // Building two separately-constructed layouts here would make an
+        // allocated set layout-incompatible with the pipeline it's bound
+        // against.
+        let bindings: Vec<Binding<DescriptorType>> = data
+            .iter()
+            .map(|view| Binding {
+                binding: view.binding,
+                data: view.ty,
+            }).collect();
+        let layout = self
+            .create_layout(&bindings, ShaderStage::All)
+            .inner
+            .downcast_ref::<VulkanLayout>()
+            .expect("Vulkan descriptor set layout")
+            .handle;
+        NativePool {
+            inner: Box::new(VulkanPool {
+                ctx: self.clone(),
+                pool,
+                layout,
+            }),
+        }
+    }
+}
+
+/// A descriptor-set layout plus the bindings it was built from, so a pipeline
+/// layout can be derived from it later.
+pub struct VulkanLayout {
+    pub handle: vk::DescriptorSetLayout,
+    pub bindings: Vec<Binding<DescriptorType>>,
+}
+
+impl LayoutApi for VulkanLayout {
+    fn bindings(&self) -> &[Binding<DescriptorType>] {
+        &self.bindings
+    }
+}
+
+/// Shared by both `create_pool` above (the layout sets are allocated against)
+/// and the Vulkan pipeline builder's `set_layout` (the layout baked into the
+/// pipeline layout) so the two stay identically defined.
+impl CreateLayout for Context {
+    fn create_layout(
+        &self,
+        bindings: &[Binding<DescriptorType>],
+        stage: ShaderStage,
+    ) -> NativeLayout {
+        let stage_flags = to_vk_stage_flags(stage);
+        let vk_bindings: Vec<vk::DescriptorSetLayoutBinding> = bindings
+            .iter()
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding: binding.binding,
+                descriptor_type: to_vk_descriptor_type(binding.data),
+                descriptor_count: 1,
+                stage_flags,
+                p_immutable_samplers: ptr::null(),
+            }).collect();
+        let info = vk::DescriptorSetLayoutCreateInfo {
+            s_type: vk::StructureType::DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            binding_count: vk_bindings.len() as u32,
+            p_bindings: vk_bindings.as_ptr(),
+        };
+        let handle = unsafe {
+            self.device
+                .create_descriptor_set_layout(&info, None)
+                .expect("Unable to create descriptor set layout")
+        };
+        NativeLayout {
+            inner: Box::new(VulkanLayout {
+                handle,
+                bindings: bindings.to_vec(),
+            }),
+        }
+    }
+}
+
+pub struct VulkanPool {
+    ctx: Context,
+    pool: vk::DescriptorPool,
+    layout: vk::DescriptorSetLayout,
+}
+
+impl PoolApi for VulkanPool {
+    fn create_descriptor(&self, count: u32) -> Vec<DescriptorHandle> {
+        let layouts = vec![self.layout; count as usize];
+        let alloc_info = vk::DescriptorSetAllocateInfo {
+            s_type: vk::StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
+            p_next: ptr::null(),
+            descriptor_pool: self.pool,
+            descriptor_set_count: count,
+            p_set_layouts: layouts.as_ptr(),
+        };
+        let sets = unsafe {
+            self.ctx
+                .device
+                .allocate_descriptor_sets(&alloc_info)
+                .expect("Unable to allocate descriptor sets")
+        };
+        sets.into_iter()
+            .map(|set| self.ctx.register_descriptor(set))
+            .collect()
+    }
+}
+
+impl DescriptorApi for Context {
+    fn write(&self, handle: DescriptorHandle, data: &Descriptor) {
+        let set = self.descriptor_set(handle);
+        let bindings = data.descriptor_data();
+        // Keep the info structs alive for the whole `update_descriptor_sets`
+        // call; the writes below hold raw pointers into these vectors, so they
+        // must not reallocate once the writes are built.
+        let mut buffer_infos = Vec::with_capacity(bindings.len());
+        let mut image_infos = Vec::with_capacity(bindings.len());
+        for binding in &bindings {
+            match &binding.data {
+                DescriptorResource::Uniform(buffer) | DescriptorResource::Storage(buffer) => {
+                    buffer_infos.push(vk::DescriptorBufferInfo {
+                        buffer: self.buffer(*buffer),
+                        offset: 0,
+                        range: vk::WHOLE_SIZE,
+                    });
+                }
+                DescriptorResource::SampledImage { image } => {
+                    image_infos.push(vk::DescriptorImageInfo {
+                        sampler: vk::Sampler::null(),
+                        image_view: self.image_view(*image),
+                        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    });
+                }
+                DescriptorResource::CombinedImageSampler { image, sampler } => {
+                    image_infos.push(vk::DescriptorImageInfo {
+                        sampler: self.sampler(*sampler),
+                        image_view: self.image_view(*image),
+                        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    });
+                }
+            }
+        }
+        let mut buffer_idx = 0;
+        let mut image_idx = 0;
+        let writes: Vec<vk::WriteDescriptorSet> = bindings
+            .iter()
+            .map(|binding| {
+                let mut write = vk::WriteDescriptorSet {
+                    s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
+                    p_next: ptr::null(),
+                    dst_set: set,
+                    dst_binding: binding.binding,
+                    dst_array_element: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    p_image_info: ptr::null(),
+                    p_buffer_info: ptr::null(),
+                    p_texel_buffer_view: ptr::null(),
+                };
+                match &binding.data {
+                    DescriptorResource::Uniform(_) => {
+                        write.descriptor_type = vk::DescriptorType::UNIFORM_BUFFER;
+                        write.p_buffer_info = &buffer_infos[buffer_idx];
+                        buffer_idx += 1;
+                    }
+                    DescriptorResource::Storage(_) => {
+                        write.descriptor_type = vk::DescriptorType::STORAGE_BUFFER;
+                        write.p_buffer_info = &buffer_infos[buffer_idx];
+                        buffer_idx += 1;
+                    }
+                    DescriptorResource::SampledImage { .. } => {
+                        write.descriptor_type = vk::DescriptorType::SAMPLED_IMAGE;
+                        write.p_image_info = &image_infos[image_idx];
+                        image_idx += 1;
+                    }
+                    DescriptorResource::CombinedImageSampler { .. } => {
+                        write.descriptor_type = vk::DescriptorType::COMBINED_IMAGE_SAMPLER;
+                        write.p_image_info = &image_infos[image_idx];
+                        image_idx += 1;
+                    }
+                }
+                write
+            }).collect();
+        unsafe {
+            self.device.update_descriptor_sets(&writes, &[]);
+        }
+    }
+}
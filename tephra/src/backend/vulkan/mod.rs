@@ -0,0 +1,312 @@
+pub mod debug;
+pub mod descriptor;
+pub mod pipeline;
+pub mod renderpass;
+pub mod sampler;
+pub mod swapchain;
+pub mod sync;
+
+use self::debug::{validation_enabled, DebugMessenger, VALIDATION_LAYER};
+use self::sync::FrameSync;
+use ash::extensions::{DebugUtils, Surface, Swapchain as SwapchainLoader};
+use ash::version::{DeviceV1_0, EntryV1_0, InstanceV1_0};
+use ash::vk;
+use buffer::BufferHandle;
+use descriptor::DescriptorHandle;
+use image::{ImageHandle, Resolution};
+use parking_lot::Mutex;
+use renderpass::{FramebufferHandle, RenderpassCache, RenderpassHandle};
+use sampler::SamplerHandle;
+use shader::ShaderModule;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::Arc;
+
+/// Maps the crate's opaque `new_typed_handle!` handles onto the concrete
+/// Vulkan objects they stand for. Every handle the frontend hands back to a
+/// backend call (`write`, pipeline layout creation, ...) is resolved here.
+#[derive(Default)]
+pub struct Resources {
+    next: u64,
+    buffers: HashMap<BufferHandle, vk::Buffer>,
+    image_views: HashMap<ImageHandle, vk::ImageView>,
+    samplers: HashMap<SamplerHandle, vk::Sampler>,
+    // Populated by the `ShaderApi` backend when a module is compiled; read back
+    // here when a pipeline references it as a stage.
+    shaders: HashMap<ShaderModule, vk::ShaderModule>,
+    descriptors: HashMap<DescriptorHandle, vk::DescriptorSet>,
+    renderpasses: HashMap<RenderpassHandle, vk::RenderPass>,
+    framebuffers: HashMap<FramebufferHandle, vk::Framebuffer>,
+}
+
+impl Resources {
+    fn alloc_id(&mut self) -> u64 {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
+}
+
+/// Backend marker type. The generic frontend is parameterized over a
+/// [`BackendApi`](crate::backend::BackendApi); `Vulkan` is the concrete
+/// implementation.
+#[derive(Copy, Clone)]
+pub struct Vulkan;
+
+/// A device queue protected by a mutex so it can be submitted to from multiple
+/// threads. Vulkan forbids concurrent access to the same `VkQueue`.
+pub struct Queue {
+    pub inner: Mutex<vk::Queue>,
+}
+
+#[derive(Clone)]
+pub struct Context {
+    pub data: Arc<ContextData>,
+}
+
+impl Deref for Context {
+    type Target = ContextData;
+    fn deref(&self) -> &ContextData {
+        &self.data
+    }
+}
+
+pub struct ContextData {
+    pub instance: ash::Instance,
+    pub device: ash::Device,
+    pub pdevice: vk::PhysicalDevice,
+    pub surface: vk::SurfaceKHR,
+    pub surface_loader: Surface,
+    pub swapchain_loader: SwapchainLoader,
+    pub surface_format: vk::SurfaceFormatKHR,
+    pub surface_resolution: vk::Extent2D,
+    pub present_queue: Queue,
+    // Per-frame synchronization. Replaces the single
+    // `present_complete_semaphore`/`rendering_complete_semaphore` pair that
+    // used to pin the GPU to one in-flight frame.
+    pub frame_sync: Mutex<FrameSync>,
+    // `VK_EXT_debug_utils` messenger, present only when validation is enabled.
+    // Dropped (and the messenger destroyed) when the context goes away.
+    pub debug: Option<DebugMessenger>,
+    // Memoized render passes and framebuffers. Framebuffer entries are tied to
+    // the lifetime of the views that feed them (see `invalidate_image`).
+    pub renderpass_cache: Mutex<RenderpassCache>,
+    // Memoized pipelines and pipeline layouts, keyed by the builder state they
+    // were created from. Building a pipeline is expensive, so identical state
+    // hands back the same `vk::Pipeline`.
+    pub pipeline_cache: Mutex<self::pipeline::PipelineCache>,
+    // Handle -> concrete Vulkan object registry.
+    pub resources: Mutex<Resources>,
+}
+
+/// Create the Vulkan instance, opting into `VK_LAYER_KHRONOS_validation` and
+/// `VK_EXT_debug_utils` when validation is requested (either through the
+/// `validation` flag or the `TEPHRA_VALIDATION` environment variable). The
+/// returned [`DebugMessenger`], if any, must be stored on the context so it
+/// outlives every validated call and is destroyed on teardown.
+pub fn create_instance<E: EntryV1_0>(
+    entry: &E,
+    mut extensions: Vec<*const i8>,
+    validation: bool,
+) -> (ash::Instance, Option<DebugMessenger>) {
+    let validation = validation_enabled(validation);
+    let layer_names: Vec<CString> = if validation {
+        vec![CString::new(VALIDATION_LAYER).unwrap()]
+    } else {
+        Vec::new()
+    };
+    let layer_ptrs: Vec<*const i8> = layer_names.iter().map(|name| name.as_ptr()).collect();
+    if validation {
+        extensions.push(DebugUtils::name().as_ptr());
+    }
+    let app_info = vk::ApplicationInfo {
+        s_type: vk::StructureType::APPLICATION_INFO,
+        p_next: ptr::null(),
+        p_application_name: ptr::null(),
+        application_version: 0,
+        p_engine_name: ptr::null(),
+        engine_version: 0,
+        api_version: ash_vk_make_version(1, 0, 0),
+    };
+    let create_info = vk::InstanceCreateInfo {
+        s_type: vk::StructureType::INSTANCE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        p_application_info: &app_info,
+        enabled_layer_count: layer_ptrs.len() as u32,
+        pp_enabled_layer_names: layer_ptrs.as_ptr(),
+        enabled_extension_count: extensions.len() as u32,
+        pp_enabled_extension_names: extensions.as_ptr(),
+    };
+    let instance = unsafe {
+        entry
+            .create_instance(&create_info, None)
+            .expect("Unable to create Vulkan instance")
+    };
+    let debug = if validation {
+        Some(DebugMessenger::new(entry, &instance))
+    } else {
+        None
+    };
+    (instance, debug)
+}
+
+fn ash_vk_make_version(major: u32, minor: u32, patch: u32) -> u32 {
+    (major << 22) | (minor << 12) | patch
+}
+
+impl Context {
+    /// Record and submit the command buffers for the current frame.
+    ///
+    /// The acquire step signals the swapchain's per-image semaphore, so the
+    /// submission waits on exactly that semaphore before the color attachment
+    /// stage, and signals both the current frame's render-finished semaphore
+    /// (for `present` to wait on) and in-flight fence (for the next iteration
+    /// of this frame slot to wait on).
+    pub fn submit(&self, command_buffers: &[vk::CommandBuffer], acquired: vk::Semaphore) {
+        let frame_sync = self.frame_sync.lock();
+        frame_sync.wait_for_frame();
+        let wait_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
+        let render_complete = frame_sync.render_complete_semaphore();
+        let submit_info = vk::SubmitInfo {
+            s_type: vk::StructureType::SUBMIT_INFO,
+            p_next: std::ptr::null(),
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &acquired,
+            p_wait_dst_stage_mask: &wait_stage,
+            command_buffer_count: command_buffers.len() as u32,
+            p_command_buffers: command_buffers.as_ptr(),
+            signal_semaphore_count: 1,
+            p_signal_semaphores: &render_complete,
+        };
+        unsafe {
+            self.device
+                .queue_submit(
+                    *self.present_queue.inner.lock(),
+                    &[submit_info],
+                    frame_sync.in_flight_fence(),
+                ).expect("Unable to submit frame");
+        }
+    }
+
+    /// Semaphore the current frame's submission signals and `present` waits on.
+    pub fn render_complete_semaphore(&self) -> vk::Semaphore {
+        self.frame_sync.lock().render_complete_semaphore()
+    }
+
+    /// Advance to the next frame slot. Called once per presented frame.
+    pub fn advance_frame(&self) {
+        self.frame_sync.lock().advance();
+    }
+
+    /// Drop every cached framebuffer built from `image`. Called when an image
+    /// (or its view) is destroyed — e.g. for each old swapchain image on
+    /// `SwapchainData::recreate` — so the cache never hands back a framebuffer
+    /// that references a destroyed view.
+    pub fn invalidate_image(&self, image: ImageHandle) {
+        self.renderpass_cache.lock().invalidate_image(self, image);
+    }
+
+    /// Attach a readable name to a Vulkan object for validation/RenderDoc
+    /// output. A no-op unless validation (and therefore the debug messenger)
+    /// is enabled.
+    pub fn set_debug_name(&self, object_type: vk::ObjectType, object_handle: u64, name: &str) {
+        if let Some(debug) = self.debug.as_ref() {
+            debug.set_debug_name(self.device.handle(), object_type, object_handle, name);
+        }
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        Resolution {
+            width: self.surface_resolution.width,
+            height: self.surface_resolution.height,
+        }
+    }
+
+    pub fn register_buffer(&self, buffer: vk::Buffer) -> BufferHandle {
+        let mut resources = self.resources.lock();
+        let handle = BufferHandle(resources.alloc_id());
+        resources.buffers.insert(handle, buffer);
+        handle
+    }
+
+    pub fn register_image_view(&self, view: vk::ImageView) -> ImageHandle {
+        let mut resources = self.resources.lock();
+        let handle = ImageHandle(resources.alloc_id());
+        resources.image_views.insert(handle, view);
+        handle
+    }
+
+    pub fn register_sampler(&self, sampler: vk::Sampler) -> SamplerHandle {
+        let mut resources = self.resources.lock();
+        let handle = SamplerHandle(resources.alloc_id());
+        resources.samplers.insert(handle, sampler);
+        handle
+    }
+
+    pub fn register_descriptor(&self, set: vk::DescriptorSet) -> DescriptorHandle {
+        let mut resources = self.resources.lock();
+        let handle = DescriptorHandle(resources.alloc_id());
+        resources.descriptors.insert(handle, set);
+        handle
+    }
+
+    pub fn buffer(&self, handle: BufferHandle) -> vk::Buffer {
+        self.resources.lock().buffers[&handle]
+    }
+
+    pub fn image_view(&self, handle: ImageHandle) -> vk::ImageView {
+        self.resources.lock().image_views[&handle]
+    }
+
+    pub fn sampler(&self, handle: SamplerHandle) -> vk::Sampler {
+        self.resources.lock().samplers[&handle]
+    }
+
+    /// Record the compiled module a `ShaderModule` stands for. Unlike the other
+    /// resources the handle is minted by the `ShaderApi` backend (it owns the
+    /// `ShaderModule` type), so this takes the handle rather than returning one.
+    pub fn register_shader(&self, module: ShaderModule, shader: vk::ShaderModule) {
+        self.resources.lock().shaders.insert(module, shader);
+    }
+
+    pub fn shader_module(&self, module: ShaderModule) -> vk::ShaderModule {
+        self.resources.lock().shaders[&module]
+    }
+
+    pub fn descriptor_set(&self, handle: DescriptorHandle) -> vk::DescriptorSet {
+        self.resources.lock().descriptors[&handle]
+    }
+
+    pub fn register_renderpass(&self, renderpass: vk::RenderPass) -> RenderpassHandle {
+        let mut resources = self.resources.lock();
+        let handle = RenderpassHandle(resources.alloc_id());
+        resources.renderpasses.insert(handle, renderpass);
+        handle
+    }
+
+    pub fn register_framebuffer(&self, framebuffer: vk::Framebuffer) -> FramebufferHandle {
+        let mut resources = self.resources.lock();
+        let handle = FramebufferHandle(resources.alloc_id());
+        resources.framebuffers.insert(handle, framebuffer);
+        handle
+    }
+
+    /// Remove `handle` from the registry, handing back the `vk::Framebuffer`
+    /// it stood for so the caller can destroy it. Used by
+    /// `FramebufferApi::destroy_framebuffer` when the render pass cache
+    /// invalidates an entry.
+    pub fn unregister_framebuffer(&self, handle: FramebufferHandle) -> Option<vk::Framebuffer> {
+        self.resources.lock().framebuffers.remove(&handle)
+    }
+
+    pub fn renderpass(&self, handle: RenderpassHandle) -> vk::RenderPass {
+        self.resources.lock().renderpasses[&handle]
+    }
+
+    pub fn framebuffer(&self, handle: FramebufferHandle) -> vk::Framebuffer {
+        self.resources.lock().framebuffers[&handle]
+    }
+}
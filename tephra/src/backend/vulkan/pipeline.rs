@@ -0,0 +1,457 @@
+use super::Context;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use descriptor::{Binding, CreateLayout, DescriptorType, ShaderStage};
+use downcast::Downcast;
+use image::ImageLayout;
+use pipeline::{
+    ColorBlendAttachment, ComputePipelineState, CreatePipeline, CullMode, DepthStencilState,
+    FrontFace, Pipeline, PipelineApi, PipelineState, PolygonMode, PrimitiveTopology,
+    RasterizationState, VertexFormat,
+};
+use renderpass::{AttachmentDescription, Format, LoadOp, RenderpassKey, StoreOp};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::ptr;
+
+/// Memoizes pipelines, pipeline layouts and descriptor set layouts so that
+/// identical builder state is only ever turned into one set of Vulkan objects.
+/// Owned by the [`Context`](super::Context).
+#[derive(Default)]
+pub struct PipelineCache {
+    set_layouts: HashMap<Vec<Binding<DescriptorType>>, vk::DescriptorSetLayout>,
+    pipeline_layouts: HashMap<Vec<Binding<DescriptorType>>, vk::PipelineLayout>,
+    graphics: HashMap<PipelineState, vk::Pipeline>,
+    compute: HashMap<ComputePipelineState, vk::Pipeline>,
+}
+
+/// A compiled pipeline plus the layout it was created with and the point it
+/// binds at, resolved back from [`Pipeline::downcast`](pipeline::Pipeline).
+pub struct VulkanPipeline {
+    pub pipeline: vk::Pipeline,
+    pub layout: vk::PipelineLayout,
+    pub bind_point: vk::PipelineBindPoint,
+}
+
+impl PipelineApi for VulkanPipeline {}
+
+fn to_vk_topology(topology: PrimitiveTopology) -> vk::PrimitiveTopology {
+    match topology {
+        PrimitiveTopology::PointList => vk::PrimitiveTopology::POINT_LIST,
+        PrimitiveTopology::LineList => vk::PrimitiveTopology::LINE_LIST,
+        PrimitiveTopology::TriangleList => vk::PrimitiveTopology::TRIANGLE_LIST,
+        PrimitiveTopology::TriangleStrip => vk::PrimitiveTopology::TRIANGLE_STRIP,
+    }
+}
+
+fn to_vk_polygon_mode(mode: PolygonMode) -> vk::PolygonMode {
+    match mode {
+        PolygonMode::Fill => vk::PolygonMode::FILL,
+        PolygonMode::Line => vk::PolygonMode::LINE,
+        PolygonMode::Point => vk::PolygonMode::POINT,
+    }
+}
+
+fn to_vk_cull_mode(mode: CullMode) -> vk::CullModeFlags {
+    match mode {
+        CullMode::None => vk::CullModeFlags::NONE,
+        CullMode::Front => vk::CullModeFlags::FRONT,
+        CullMode::Back => vk::CullModeFlags::BACK,
+    }
+}
+
+fn to_vk_front_face(front_face: FrontFace) -> vk::FrontFace {
+    match front_face {
+        FrontFace::CounterClockwise => vk::FrontFace::COUNTER_CLOCKWISE,
+        FrontFace::Clockwise => vk::FrontFace::CLOCKWISE,
+    }
+}
+
+fn to_vk_vertex_format(format: VertexFormat) -> vk::Format {
+    match format {
+        VertexFormat::Float => vk::Format::R32_SFLOAT,
+        VertexFormat::Vec2 => vk::Format::R32G32_SFLOAT,
+        VertexFormat::Vec3 => vk::Format::R32G32B32_SFLOAT,
+        VertexFormat::Vec4 => vk::Format::R32G32B32A32_SFLOAT,
+    }
+}
+
+/// Map the swapchain surface format onto the backend-agnostic attachment format
+/// so the graphics pipeline can be validated against a compatible render pass.
+fn surface_format(ctx: &Context) -> Format {
+    match ctx.surface_format.format {
+        vk::Format::R8G8B8A8_UNORM => Format::Rgba8Unorm,
+        vk::Format::B8G8R8A8_SRGB => Format::Bgra8Srgb,
+        _ => Format::Bgra8Unorm,
+    }
+}
+
+/// Descriptor set layout for the pipeline's bound descriptor set, cached on
+/// the set of bindings. Built through `CreateLayout` — the same path
+/// `descriptor::CreatePool::create_pool` uses to build the layout sets are
+/// allocated against — so a set allocated from the pool is never
+/// layout-incompatible with the pipeline it's bound to.
+fn set_layout(ctx: &Context, bindings: &[Binding<DescriptorType>]) -> vk::DescriptorSetLayout {
+    if let Some(&layout) = ctx.pipeline_cache.lock().set_layouts.get(bindings) {
+        return layout;
+    }
+    let layout = ctx
+        .create_layout(bindings, ShaderStage::All)
+        .inner
+        .downcast_ref::<super::descriptor::VulkanLayout>()
+        .expect("Vulkan descriptor set layout")
+        .handle;
+    ctx.pipeline_cache
+        .lock()
+        .set_layouts
+        .insert(bindings.to_vec(), layout);
+    layout
+}
+
+/// Pipeline layout derived from the descriptor set bound to the pipeline.
+fn pipeline_layout(ctx: &Context, bindings: &[Binding<DescriptorType>]) -> vk::PipelineLayout {
+    if let Some(&layout) = ctx.pipeline_cache.lock().pipeline_layouts.get(bindings) {
+        return layout;
+    }
+    let set_layouts = if bindings.is_empty() {
+        Vec::new()
+    } else {
+        vec![set_layout(ctx, bindings)]
+    };
+    let info = vk::PipelineLayoutCreateInfo {
+        s_type: vk::StructureType::PIPELINE_LAYOUT_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        set_layout_count: set_layouts.len() as u32,
+        p_set_layouts: set_layouts.as_ptr(),
+        push_constant_range_count: 0,
+        p_push_constant_ranges: ptr::null(),
+    };
+    let layout = unsafe {
+        ctx.device
+            .create_pipeline_layout(&info, None)
+            .expect("Unable to create pipeline layout")
+    };
+    ctx.pipeline_cache
+        .lock()
+        .pipeline_layouts
+        .insert(bindings.to_vec(), layout);
+    layout
+}
+
+/// A render pass compatible with the pipeline: a single color attachment in the
+/// surface format. Obtained through the render pass cache so it is shared with
+/// the framebuffers rendered into it.
+///
+/// The attachment is cleared at the start of the pass, so its contents going
+/// in don't matter (`Undefined`), and it is handed straight to the
+/// presentation engine afterwards, so it must leave the pass in `Present`
+/// rather than the `Color` (attachment-optimal) layout it is rendered in.
+fn compatible_renderpass(ctx: &Context) -> vk::RenderPass {
+    let key = RenderpassKey {
+        attachments: vec![AttachmentDescription {
+            format: surface_format(ctx),
+            load_op: LoadOp::Clear,
+            store_op: StoreOp::Store,
+            initial_layout: ImageLayout::Undefined,
+            final_layout: ImageLayout::Present,
+        }],
+    };
+    let handle = ctx.renderpass_cache.lock().renderpass(ctx, key);
+    ctx.renderpass(handle)
+}
+
+impl CreatePipeline for Context {
+    fn from_pipeline_builder(&self, pipeline_builder: PipelineState) -> Pipeline {
+        let layout = pipeline_layout(self, &pipeline_builder.layout);
+        if let Some(&pipeline) = self.pipeline_cache.lock().graphics.get(&pipeline_builder) {
+            return Pipeline {
+                data: Box::new(VulkanPipeline {
+                    pipeline,
+                    layout,
+                    bind_point: vk::PipelineBindPoint::GRAPHICS,
+                }),
+            };
+        }
+
+        let entry = CStr::from_bytes_with_nul(b"main\0").unwrap();
+        let mut stages = Vec::new();
+        if let Some(module) = pipeline_builder.vertex_shader {
+            stages.push(shader_stage(
+                self.shader_module(module),
+                vk::ShaderStageFlags::VERTEX,
+                entry,
+            ));
+        }
+        if let Some(module) = pipeline_builder.fragment_shader {
+            stages.push(shader_stage(
+                self.shader_module(module),
+                vk::ShaderStageFlags::FRAGMENT,
+                entry,
+            ));
+        }
+
+        let vertex_bindings: Vec<vk::VertexInputBindingDescription> = pipeline_builder
+            .vertex_input
+            .iter()
+            .map(|binding| vk::VertexInputBindingDescription {
+                binding: binding.binding,
+                stride: binding.stride,
+                input_rate: vk::VertexInputRate::VERTEX,
+            }).collect();
+        let vertex_attributes: Vec<vk::VertexInputAttributeDescription> = pipeline_builder
+            .vertex_input
+            .iter()
+            .flat_map(|binding| {
+                let slot = binding.binding;
+                binding
+                    .attributes
+                    .iter()
+                    .map(move |attr| vk::VertexInputAttributeDescription {
+                        location: attr.location,
+                        binding: slot,
+                        format: to_vk_vertex_format(attr.format),
+                        offset: attr.offset,
+                    })
+            }).collect();
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            vertex_binding_description_count: vertex_bindings.len() as u32,
+            p_vertex_binding_descriptions: vertex_bindings.as_ptr(),
+            vertex_attribute_description_count: vertex_attributes.len() as u32,
+            p_vertex_attribute_descriptions: vertex_attributes.as_ptr(),
+        };
+
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            topology: to_vk_topology(pipeline_builder.topology),
+            primitive_restart_enable: vk::FALSE,
+        };
+
+        // Viewport and scissor are dynamic (see `dynamic_state` below) rather
+        // than baked in from `self.surface_resolution`: the pipeline is cached
+        // forever keyed on `PipelineState`, which has no resolution component,
+        // so a baked-in viewport would go stale the moment
+        // `SwapchainData::recreate` picks a new extent. The caller must issue
+        // `vkCmdSetViewport`/`vkCmdSetScissor` before any draw using this
+        // pipeline.
+        let viewport_state = vk::PipelineViewportStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_VIEWPORT_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            viewport_count: 1,
+            p_viewports: ptr::null(),
+            scissor_count: 1,
+            p_scissors: ptr::null(),
+        };
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            dynamic_state_count: dynamic_states.len() as u32,
+            p_dynamic_states: dynamic_states.as_ptr(),
+        };
+
+        let rasterization = rasterization_state(pipeline_builder.rasterization);
+        let multisample = vk::PipelineMultisampleStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            rasterization_samples: vk::SampleCountFlags::TYPE_1,
+            sample_shading_enable: vk::FALSE,
+            min_sample_shading: 0.0,
+            p_sample_mask: ptr::null(),
+            alpha_to_coverage_enable: vk::FALSE,
+            alpha_to_one_enable: vk::FALSE,
+        };
+        let depth_stencil = depth_stencil_state(pipeline_builder.depth_stencil);
+
+        // Default to a single opaque attachment when the builder declares none,
+        // matching the single color attachment of `compatible_renderpass`.
+        let blend_attachments: Vec<vk::PipelineColorBlendAttachmentState> =
+            if pipeline_builder.color_blend.is_empty() {
+                vec![color_blend_attachment(ColorBlendAttachment::default())]
+            } else {
+                pipeline_builder
+                    .color_blend
+                    .iter()
+                    .map(|&a| color_blend_attachment(a))
+                    .collect()
+            };
+        let color_blend = vk::PipelineColorBlendStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            logic_op_enable: vk::FALSE,
+            logic_op: vk::LogicOp::CLEAR,
+            attachment_count: blend_attachments.len() as u32,
+            p_attachments: blend_attachments.as_ptr(),
+            blend_constants: [0.0; 4],
+        };
+
+        let renderpass = compatible_renderpass(self);
+        let create_info = vk::GraphicsPipelineCreateInfo {
+            s_type: vk::StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            stage_count: stages.len() as u32,
+            p_stages: stages.as_ptr(),
+            p_vertex_input_state: &vertex_input,
+            p_input_assembly_state: &input_assembly,
+            p_tessellation_state: ptr::null(),
+            p_viewport_state: &viewport_state,
+            p_rasterization_state: &rasterization,
+            p_multisample_state: &multisample,
+            p_depth_stencil_state: &depth_stencil,
+            p_color_blend_state: &color_blend,
+            p_dynamic_state: &dynamic_state,
+            layout,
+            render_pass: renderpass,
+            subpass: 0,
+            base_pipeline_handle: vk::Pipeline::null(),
+            base_pipeline_index: -1,
+        };
+        let pipeline = unsafe {
+            self.device
+                .create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .expect("Unable to create graphics pipeline")[0]
+        };
+        self.pipeline_cache
+            .lock()
+            .graphics
+            .insert(pipeline_builder, pipeline);
+        Pipeline {
+            data: Box::new(VulkanPipeline {
+                pipeline,
+                layout,
+                bind_point: vk::PipelineBindPoint::GRAPHICS,
+            }),
+        }
+    }
+
+    fn from_compute_builder(&self, pipeline_builder: ComputePipelineState) -> Pipeline {
+        let layout = pipeline_layout(self, &pipeline_builder.layout);
+        if let Some(&pipeline) = self.pipeline_cache.lock().compute.get(&pipeline_builder) {
+            return Pipeline {
+                data: Box::new(VulkanPipeline {
+                    pipeline,
+                    layout,
+                    bind_point: vk::PipelineBindPoint::COMPUTE,
+                }),
+            };
+        }
+        let entry = CStr::from_bytes_with_nul(b"main\0").unwrap();
+        let module = pipeline_builder
+            .compute_shader
+            .expect("Compute pipeline without a compute shader");
+        let stage = shader_stage(
+            self.shader_module(module),
+            vk::ShaderStageFlags::COMPUTE,
+            entry,
+        );
+        let create_info = vk::ComputePipelineCreateInfo {
+            s_type: vk::StructureType::COMPUTE_PIPELINE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            stage,
+            layout,
+            base_pipeline_handle: vk::Pipeline::null(),
+            base_pipeline_index: -1,
+        };
+        let pipeline = unsafe {
+            self.device
+                .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .expect("Unable to create compute pipeline")[0]
+        };
+        self.pipeline_cache
+            .lock()
+            .compute
+            .insert(pipeline_builder, pipeline);
+        Pipeline {
+            data: Box::new(VulkanPipeline {
+                pipeline,
+                layout,
+                bind_point: vk::PipelineBindPoint::COMPUTE,
+            }),
+        }
+    }
+}
+
+fn shader_stage(
+    module: vk::ShaderModule,
+    stage: vk::ShaderStageFlags,
+    entry: &CStr,
+) -> vk::PipelineShaderStageCreateInfo {
+    vk::PipelineShaderStageCreateInfo {
+        s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        stage,
+        module,
+        p_name: entry.as_ptr(),
+        p_specialization_info: ptr::null(),
+    }
+}
+
+fn rasterization_state(state: RasterizationState) -> vk::PipelineRasterizationStateCreateInfo {
+    vk::PipelineRasterizationStateCreateInfo {
+        s_type: vk::StructureType::PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        depth_clamp_enable: vk::FALSE,
+        rasterizer_discard_enable: vk::FALSE,
+        polygon_mode: to_vk_polygon_mode(state.polygon_mode),
+        cull_mode: to_vk_cull_mode(state.cull_mode),
+        front_face: to_vk_front_face(state.front_face),
+        depth_bias_enable: vk::FALSE,
+        depth_bias_constant_factor: 0.0,
+        depth_bias_clamp: 0.0,
+        depth_bias_slope_factor: 0.0,
+        line_width: 1.0,
+    }
+}
+
+fn depth_stencil_state(state: DepthStencilState) -> vk::PipelineDepthStencilStateCreateInfo {
+    vk::PipelineDepthStencilStateCreateInfo {
+        s_type: vk::StructureType::PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        depth_test_enable: bool_to_vk(state.depth_test),
+        depth_write_enable: bool_to_vk(state.depth_write),
+        depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
+        depth_bounds_test_enable: vk::FALSE,
+        stencil_test_enable: vk::FALSE,
+        front: Default::default(),
+        back: Default::default(),
+        min_depth_bounds: 0.0,
+        max_depth_bounds: 1.0,
+    }
+}
+
+fn color_blend_attachment(
+    attachment: ColorBlendAttachment,
+) -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState {
+        blend_enable: bool_to_vk(attachment.blend),
+        src_color_blend_factor: vk::BlendFactor::SRC_ALPHA,
+        dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        color_blend_op: vk::BlendOp::ADD,
+        src_alpha_blend_factor: vk::BlendFactor::ONE,
+        dst_alpha_blend_factor: vk::BlendFactor::ZERO,
+        alpha_blend_op: vk::BlendOp::ADD,
+        color_write_mask: vk::ColorComponentFlags::all(),
+    }
+}
+
+fn bool_to_vk(value: bool) -> vk::Bool32 {
+    if value {
+        vk::TRUE
+    } else {
+        vk::FALSE
+    }
+}
@@ -0,0 +1,165 @@
+use super::Context;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use image::ImageLayout;
+use renderpass::{
+    AttachmentDescription, Format, FramebufferApi, FramebufferHandle, FramebufferKey, LoadOp,
+    RenderpassApi, RenderpassHandle, RenderpassKey, StoreOp,
+};
+use std::ptr;
+
+fn to_vk_format(format: Format) -> vk::Format {
+    match format {
+        Format::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
+        Format::Bgra8Unorm => vk::Format::B8G8R8A8_UNORM,
+        Format::Bgra8Srgb => vk::Format::B8G8R8A8_SRGB,
+        Format::D32Float => vk::Format::D32_SFLOAT,
+    }
+}
+
+fn to_vk_load_op(op: LoadOp) -> vk::AttachmentLoadOp {
+    match op {
+        LoadOp::Load => vk::AttachmentLoadOp::LOAD,
+        LoadOp::Clear => vk::AttachmentLoadOp::CLEAR,
+        LoadOp::DontCare => vk::AttachmentLoadOp::DONT_CARE,
+    }
+}
+
+fn to_vk_store_op(op: StoreOp) -> vk::AttachmentStoreOp {
+    match op {
+        StoreOp::Store => vk::AttachmentStoreOp::STORE,
+        StoreOp::DontCare => vk::AttachmentStoreOp::DONT_CARE,
+    }
+}
+
+fn to_vk_layout(layout: ImageLayout) -> vk::ImageLayout {
+    match layout {
+        ImageLayout::Color => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        ImageLayout::Undefined => vk::ImageLayout::UNDEFINED,
+        ImageLayout::Present => vk::ImageLayout::PRESENT_SRC_KHR,
+        _ => vk::ImageLayout::GENERAL,
+    }
+}
+
+fn is_depth(format: Format) -> bool {
+    match format {
+        Format::D32Float => true,
+        _ => false,
+    }
+}
+
+/// Build (but do not register) the `vk::RenderPass` for `key`. A single subpass
+/// referencing every color attachment, plus the first depth attachment if one
+/// is present.
+fn build_render_pass(ctx: &Context, key: &RenderpassKey) -> vk::RenderPass {
+    let attachments: Vec<vk::AttachmentDescription> = key
+        .attachments
+        .iter()
+        .map(|a: &AttachmentDescription| vk::AttachmentDescription {
+            flags: Default::default(),
+            format: to_vk_format(a.format),
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: to_vk_load_op(a.load_op),
+            store_op: to_vk_store_op(a.store_op),
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: to_vk_layout(a.initial_layout),
+            final_layout: to_vk_layout(a.final_layout),
+        }).collect();
+
+    let color_refs: Vec<vk::AttachmentReference> = key
+        .attachments
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| !is_depth(a.format))
+        .map(|(i, _)| vk::AttachmentReference {
+            attachment: i as u32,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }).collect();
+    let depth_ref = key
+        .attachments
+        .iter()
+        .position(|a| is_depth(a.format))
+        .map(|i| vk::AttachmentReference {
+            attachment: i as u32,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        });
+
+    let subpass = vk::SubpassDescription {
+        flags: Default::default(),
+        pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+        input_attachment_count: 0,
+        p_input_attachments: ptr::null(),
+        color_attachment_count: color_refs.len() as u32,
+        p_color_attachments: color_refs.as_ptr(),
+        p_resolve_attachments: ptr::null(),
+        p_depth_stencil_attachment: depth_ref
+            .as_ref()
+            .map(|r| r as *const _)
+            .unwrap_or(ptr::null()),
+        preserve_attachment_count: 0,
+        p_preserve_attachments: ptr::null(),
+    };
+    let create_info = vk::RenderPassCreateInfo {
+        s_type: vk::StructureType::RENDER_PASS_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: Default::default(),
+        attachment_count: attachments.len() as u32,
+        p_attachments: attachments.as_ptr(),
+        subpass_count: 1,
+        p_subpasses: &subpass,
+        dependency_count: 0,
+        p_dependencies: ptr::null(),
+    };
+    unsafe {
+        ctx.device
+            .create_render_pass(&create_info, None)
+            .expect("Unable to create render pass")
+    }
+}
+
+impl RenderpassApi for Context {
+    fn create_renderpass(&self, key: &RenderpassKey) -> RenderpassHandle {
+        let renderpass = build_render_pass(self, key);
+        self.register_renderpass(renderpass)
+    }
+}
+
+impl FramebufferApi for Context {
+    fn create_framebuffer(
+        &self,
+        key: &FramebufferKey,
+        renderpass: RenderpassHandle,
+    ) -> FramebufferHandle {
+        // Use the render pass the cache already memoized for `key.renderpass`
+        // instead of building a second, unregistered one here.
+        let renderpass = self.renderpass(renderpass);
+        let attachments: Vec<vk::ImageView> =
+            key.views.iter().map(|&view| self.image_view(view)).collect();
+        let create_info = vk::FramebufferCreateInfo {
+            s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            render_pass: renderpass,
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            width: key.width,
+            height: key.height,
+            layers: 1,
+        };
+        let framebuffer = unsafe {
+            self.device
+                .create_framebuffer(&create_info, None)
+                .expect("Unable to create framebuffer")
+        };
+        self.register_framebuffer(framebuffer)
+    }
+
+    fn destroy_framebuffer(&self, handle: FramebufferHandle) {
+        if let Some(framebuffer) = self.unregister_framebuffer(handle) {
+            unsafe {
+                self.device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use super::Context;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use sampler::{AddressMode, CreateSampler, Filter, MipmapMode, SamplerDesc, SamplerHandle};
+use std::ptr;
+
+fn to_vk_filter(filter: Filter) -> vk::Filter {
+    match filter {
+        Filter::Nearest => vk::Filter::NEAREST,
+        Filter::Linear => vk::Filter::LINEAR,
+    }
+}
+
+fn to_vk_mipmap_mode(mode: MipmapMode) -> vk::SamplerMipmapMode {
+    match mode {
+        MipmapMode::Nearest => vk::SamplerMipmapMode::NEAREST,
+        MipmapMode::Linear => vk::SamplerMipmapMode::LINEAR,
+    }
+}
+
+fn to_vk_address_mode(mode: AddressMode) -> vk::SamplerAddressMode {
+    match mode {
+        AddressMode::Repeat => vk::SamplerAddressMode::REPEAT,
+        AddressMode::MirroredRepeat => vk::SamplerAddressMode::MIRRORED_REPEAT,
+        AddressMode::ClampToEdge => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        AddressMode::ClampToBorder => vk::SamplerAddressMode::CLAMP_TO_BORDER,
+    }
+}
+
+impl CreateSampler for Context {
+    fn create_sampler(&self, desc: SamplerDesc) -> SamplerHandle {
+        let address_mode = to_vk_address_mode(desc.address_mode);
+        let create_info = vk::SamplerCreateInfo {
+            s_type: vk::StructureType::SAMPLER_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            mag_filter: to_vk_filter(desc.mag_filter),
+            min_filter: to_vk_filter(desc.min_filter),
+            mipmap_mode: to_vk_mipmap_mode(desc.mipmap_mode),
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mip_lod_bias: 0.0,
+            anisotropy_enable: vk::FALSE,
+            max_anisotropy: 1.0,
+            compare_enable: vk::FALSE,
+            compare_op: vk::CompareOp::ALWAYS,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            border_color: vk::BorderColor::FLOAT_OPAQUE_BLACK,
+            unnormalized_coordinates: vk::FALSE,
+        };
+        let sampler = unsafe {
+            self.device
+                .create_sampler(&create_info, None)
+                .expect("Unable to create sampler")
+        };
+        self.register_sampler(sampler)
+    }
+}
@@ -4,19 +4,86 @@ use super::Vulkan;
 use ash::version::DeviceV1_0;
 use ash::vk;
 use image::{Image, ImageDesc, ImageLayout, Resolution};
+use log::error;
+use std::cell::Cell;
 use std::ops::Drop;
 use std::ptr;
-use swapchain::{CreateSwapchain, Swapchain, SwapchainApi, SwapchainError};
+use swapchain::{CreateSwapchain, Swapchain, SwapchainApi, SwapchainConfig, SwapchainError};
+
+/// Maps a backend-agnostic [`SwapchainConfig`] present mode onto its Vulkan
+/// counterpart. A requested mode is only honoured if the surface reports it as
+/// supported; otherwise the caller falls back to `FIFO`, which every
+/// implementation guarantees.
+fn to_vk_present_mode(mode: swapchain::PresentMode) -> vk::PresentModeKHR {
+    match mode {
+        swapchain::PresentMode::Fifo => vk::PresentModeKHR::FIFO,
+        swapchain::PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+        swapchain::PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+    }
+}
+
+fn to_vk_format(format: swapchain::Format) -> vk::Format {
+    match format {
+        swapchain::Format::Bgra8Unorm => vk::Format::B8G8R8A8_UNORM,
+        swapchain::Format::Bgra8Srgb => vk::Format::B8G8R8A8_SRGB,
+        swapchain::Format::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
+        swapchain::Format::Rgba8Srgb => vk::Format::R8G8B8A8_SRGB,
+    }
+}
+
+/// Pick the surface format to create the swapchain with. We honour the format
+/// requested through [`SwapchainConfig`], paired with the sRGB non-linear color
+/// space, when the surface advertises that pair; the color space itself is not
+/// taken from the config. Otherwise we prefer `B8G8R8A8_SRGB` over the first
+/// enumerated format (which is frequently a linear `UNORM` that washes out the
+/// presented image). A surface reporting a single `UNDEFINED` format means "anything
+/// goes", so we just take the requested format.
+fn choose_surface_format(
+    surface_formats: &[vk::SurfaceFormatKHR],
+    config: &SwapchainConfig,
+) -> vk::SurfaceFormatKHR {
+    let preferred_format = to_vk_format(config.format);
+    let preferred_color_space = vk::ColorSpaceKHR::SRGB_NONLINEAR;
+
+    if surface_formats.len() == 1 && surface_formats[0].format == vk::Format::UNDEFINED {
+        return vk::SurfaceFormatKHR {
+            format: preferred_format,
+            color_space: preferred_color_space,
+        };
+    }
+    surface_formats
+        .iter()
+        .cloned()
+        .find(|sfmt| sfmt.format == preferred_format && sfmt.color_space == preferred_color_space)
+        .or_else(|| {
+            surface_formats
+                .iter()
+                .cloned()
+                .find(|sfmt| sfmt.format == vk::Format::B8G8R8A8_SRGB)
+        }).unwrap_or_else(|| surface_formats[0].clone())
+}
 
 pub struct SwapchainData {
     pub context: Context,
     pub present_images: Vec<Image>,
     pub swapchain: vk::SwapchainKHR,
     pub resolution: Resolution,
+    pub config: SwapchainConfig,
+    // One acquire semaphore per swapchain image. `aquire_next_image` rotates
+    // through them so the semaphore handed to `acquire_next_image_khr` is never
+    // one that is still pending from an earlier, not-yet-presented frame.
+    pub acquire_semaphores: Vec<vk::Semaphore>,
+    pub acquisition_idx: Cell<usize>,
+    // Index of the semaphore handed to the most recent acquire, so the submit
+    // can wait on exactly the semaphore that acquire signaled.
+    pub acquired: Cell<usize>,
 }
 impl Drop for SwapchainData {
     fn drop(&mut self) {
         unsafe {
+            for &semaphore in &self.acquire_semaphores {
+                self.context.device.destroy_semaphore(semaphore, None);
+            }
             self.context
                 .swapchain_loader
                 .destroy_swapchain_khr(self.swapchain, None);
@@ -24,9 +91,24 @@ impl Drop for SwapchainData {
     }
 }
 
+impl SwapchainData {
+    /// Semaphore signaled by the most recent `aquire_next_image`. The submit
+    /// for this frame must wait on it so rendering does not begin before the
+    /// presentation engine is done reading the image.
+    pub fn current_acquire_semaphore(&self) -> vk::Semaphore {
+        self.acquire_semaphores[self.acquired.get()]
+    }
+}
+
 impl SwapchainApi for SwapchainData {
     fn recreate(&mut self) {
-        let new_swapchain = create_swapchain(&self.context, Some(self.swapchain));
+        // The old present images (and their views) are about to be dropped, so
+        // drop every framebuffer the cache built from them first.
+        for image in &self.present_images {
+            self.context.invalidate_image(image.handle());
+        }
+        let new_swapchain =
+            create_swapchain(&self.context, self.config.clone(), Some(self.swapchain));
         *self = new_swapchain;
     }
     fn resolution(&self) -> Resolution {
@@ -37,40 +119,54 @@ impl SwapchainApi for SwapchainData {
     }
     fn aquire_next_image(&self) -> Result<u32, SwapchainError> {
         unsafe {
+            let idx = self.acquisition_idx.get();
+            self.acquisition_idx
+                .set((idx + 1) % self.acquire_semaphores.len());
+            self.acquired.set(idx);
             self.context
                 .swapchain_loader
                 .acquire_next_image_khr(
                     self.swapchain,
                     std::u64::MAX,
-                    self.context.present_complete_semaphore,
+                    self.acquire_semaphores[idx],
                     vk::Fence::null(),
                 ).map_err(|err| match err {
                     vk::Result::ERROR_OUT_OF_DATE_KHR => SwapchainError::OutOfDate,
                     vk::Result::SUBOPTIMAL_KHR => SwapchainError::Suboptimal,
                     err => {
-                        println!("{:?}", err);
-                        println!("{:?}", vk::Result::ERROR_OUT_OF_DATE_KHR);
+                        error!("vkAcquireNextImageKHR failed: {:?}", err);
                         SwapchainError::Unknown
                     }
                 })
         }
     }
-    fn present(&self, index: u32) {
+    fn present(&self, index: u32) -> Result<(), SwapchainError> {
         unsafe {
+            // Wait on the render-finished semaphore the submit signaled for the
+            // frame that rendered into this image, then advance the ring.
+            let render_complete = self.context.render_complete_semaphore();
             let present_info = vk::PresentInfoKHR {
                 s_type: vk::StructureType::PRESENT_INFO_KHR,
                 p_next: ptr::null(),
                 wait_semaphore_count: 1,
-                p_wait_semaphores: &self.context.rendering_complete_semaphore,
+                p_wait_semaphores: &render_complete,
                 swapchain_count: 1,
                 p_swapchains: &self.swapchain,
                 p_image_indices: &index,
                 p_results: ptr::null_mut(),
             };
-            self.context
+            let result = self
+                .context
                 .swapchain_loader
                 .queue_present_khr(*self.context.present_queue.inner.lock(), &present_info)
-                .unwrap();
+                .map(|_| ())
+                .map_err(|err| match err {
+                    vk::Result::ERROR_OUT_OF_DATE_KHR => SwapchainError::OutOfDate,
+                    vk::Result::SUBOPTIMAL_KHR => SwapchainError::Suboptimal,
+                    _ => SwapchainError::Unknown,
+                });
+            self.context.advance_frame();
+            result
         }
     }
 }
@@ -127,27 +223,24 @@ unsafe fn get_swapchain_images(
             }
         }).collect()
 }
-fn create_swapchain(ctx: &Context, old_swapchain: Option<vk::SwapchainKHR>) -> SwapchainData {
+fn create_swapchain(
+    ctx: &Context,
+    config: SwapchainConfig,
+    old_swapchain: Option<vk::SwapchainKHR>,
+) -> SwapchainData {
     unsafe {
         let surface_formats = ctx
             .surface_loader
             .get_physical_device_surface_formats_khr(ctx.pdevice, ctx.surface)
             .unwrap();
-        let surface_format = surface_formats
-            .iter()
-            .map(|sfmt| match sfmt.format {
-                vk::Format::UNDEFINED => vk::SurfaceFormatKHR {
-                    format: vk::Format::B8G8R8_UNORM,
-                    color_space: sfmt.color_space,
-                },
-                _ => sfmt.clone(),
-            }).nth(0)
-            .expect("Unable to find suitable surface format.");
+        let surface_format = choose_surface_format(&surface_formats, &config);
         let surface_capabilities = ctx
             .surface_loader
             .get_physical_device_surface_capabilities_khr(ctx.pdevice, ctx.surface)
             .unwrap();
-        let mut desired_image_count = surface_capabilities.min_image_count + 1;
+        // Honour the requested image count but keep it within what the surface
+        // can actually provide. A `max_image_count` of 0 means "no upper bound".
+        let mut desired_image_count = config.image_count.max(surface_capabilities.min_image_count);
         if surface_capabilities.max_image_count > 0
             && desired_image_count > surface_capabilities.max_image_count
         {
@@ -169,10 +262,11 @@ fn create_swapchain(ctx: &Context, old_swapchain: Option<vk::SwapchainKHR>) -> S
             .surface_loader
             .get_physical_device_surface_present_modes_khr(ctx.pdevice, ctx.surface)
             .unwrap();
+        let preferred_present_mode = to_vk_present_mode(config.present_mode);
         let present_mode = present_modes
             .iter()
             .cloned()
-            .find(|&mode| mode == vk::PresentModeKHR::MAILBOX)
+            .find(|&mode| mode == preferred_present_mode)
             .unwrap_or(vk::PresentModeKHR::FIFO);
         let swapchain_loader = ash::extensions::Swapchain::new(&ctx.instance, &ctx.device)
             .expect("Unable to load swapchain");
@@ -205,18 +299,37 @@ fn create_swapchain(ctx: &Context, old_swapchain: Option<vk::SwapchainKHR>) -> S
             height: surface_resolution.height,
         };
         let present_images = get_swapchain_images(ctx, swapchain, resolution);
+        let acquire_semaphores = create_acquire_semaphores(ctx, present_images.len());
         SwapchainData {
             context: ctx.clone(),
             swapchain,
             present_images,
             resolution,
+            config,
+            acquire_semaphores,
+            acquisition_idx: Cell::new(0),
+            acquired: Cell::new(0),
         }
     }
 }
 
+unsafe fn create_acquire_semaphores(ctx: &Context, count: usize) -> Vec<vk::Semaphore> {
+    let create_info = vk::SemaphoreCreateInfo {
+        s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: Default::default(),
+    };
+    (0..count)
+        .map(|_| {
+            ctx.device
+                .create_semaphore(&create_info, None)
+                .expect("Unable to create acquire semaphore")
+        }).collect()
+}
+
 impl CreateSwapchain for Context {
-    fn new(&self) -> Swapchain {
-        let data = create_swapchain(self, None);
+    fn new(&self, config: SwapchainConfig) -> Swapchain {
+        let data = create_swapchain(self, config, None);
         Swapchain {
             data: Box::new(data),
         }
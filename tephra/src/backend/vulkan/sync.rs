@@ -0,0 +1,104 @@
+use super::Context;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::ops::Drop;
+use std::ptr;
+
+/// Number of frames the CPU is allowed to stay ahead of the GPU. Two is enough
+/// to overlap recording of frame N+1 with presentation of frame N while keeping
+/// latency low.
+pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Owns the per-frame synchronization primitives used to overlap CPU and GPU
+/// work. Without this every acquire/present reused a single pair of semaphores
+/// on the `Context`, which pinned the GPU to one in-flight frame.
+///
+/// There is no `VK_KHR_timeline_semaphore` path: every frame slot uses an
+/// in-flight fence plus a binary render-finished semaphore, whether or not the
+/// extension is available.
+pub struct FrameSync {
+    context: Context,
+    render_complete: [vk::Semaphore; MAX_FRAMES_IN_FLIGHT],
+    in_flight: [vk::Fence; MAX_FRAMES_IN_FLIGHT],
+    frame: usize,
+}
+
+impl FrameSync {
+    pub fn new(context: &Context) -> Self {
+        unsafe {
+            let semaphore_info = vk::SemaphoreCreateInfo {
+                s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: Default::default(),
+            };
+            // Fences start signaled so the very first `wait_for_frame` returns
+            // immediately instead of deadlocking on a frame that never ran.
+            let fence_info = vk::FenceCreateInfo {
+                s_type: vk::StructureType::FENCE_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: vk::FenceCreateFlags::SIGNALED,
+            };
+            let mut render_complete = [vk::Semaphore::null(); MAX_FRAMES_IN_FLIGHT];
+            let mut in_flight = [vk::Fence::null(); MAX_FRAMES_IN_FLIGHT];
+            for i in 0..MAX_FRAMES_IN_FLIGHT {
+                render_complete[i] = context
+                    .device
+                    .create_semaphore(&semaphore_info, None)
+                    .expect("Unable to create render-complete semaphore");
+                in_flight[i] = context
+                    .device
+                    .create_fence(&fence_info, None)
+                    .expect("Unable to create in-flight fence");
+            }
+            FrameSync {
+                context: context.clone(),
+                render_complete,
+                in_flight,
+                frame: 0,
+            }
+        }
+    }
+
+    /// Fence to signal on queue submit for the current frame.
+    pub fn in_flight_fence(&self) -> vk::Fence {
+        self.in_flight[self.frame]
+    }
+
+    /// Render-finished semaphore the submit signals and `present` waits on.
+    pub fn render_complete_semaphore(&self) -> vk::Semaphore {
+        self.render_complete[self.frame]
+    }
+
+    /// Block until the current frame slot is free to be recorded into.
+    pub fn wait_for_frame(&self) {
+        unsafe {
+            let fence = self.in_flight[self.frame];
+            self.context
+                .device
+                .wait_for_fences(&[fence], true, std::u64::MAX)
+                .expect("Unable to wait on in-flight fence");
+            self.context
+                .device
+                .reset_fences(&[fence])
+                .expect("Unable to reset in-flight fence");
+        }
+    }
+
+    /// Advance to the next frame slot.
+    pub fn advance(&mut self) {
+        self.frame = (self.frame + 1) % MAX_FRAMES_IN_FLIGHT;
+    }
+}
+
+impl Drop for FrameSync {
+    fn drop(&mut self) {
+        unsafe {
+            for i in 0..MAX_FRAMES_IN_FLIGHT {
+                self.context
+                    .device
+                    .destroy_semaphore(self.render_complete[i], None);
+                self.context.device.destroy_fence(self.in_flight[i], None);
+            }
+        }
+    }
+}
@@ -4,11 +4,12 @@ use std::sync::Arc;
 use crate::{
     buffer::BufferApi,
     commandbuffer::SubmitApi,
-    descriptor::{CreatePool, DescriptorApi},
+    descriptor::{CreateLayout, CreatePool, DescriptorApi},
     downcast,
     image::ImageApi,
-    pipeline::PipelineApi,
+    pipeline::{CreatePipeline, PipelineApi},
     renderpass::{FramebufferApi, RenderpassApi},
+    sampler::CreateSampler,
     shader::ShaderApi,
     swapchain::CreateSwapchain,
 };
@@ -19,10 +20,13 @@ where
         + ShaderApi
         + DescriptorApi
         + CreatePool
+        + CreateLayout
+        + CreateSampler
         + BufferApi
         + ImageApi
         + RenderpassApi
         + PipelineApi
+        + CreatePipeline
         + SubmitApi
         + FramebufferApi,
 {
@@ -2,6 +2,9 @@ use crate::{
     buffer::BufferHandle,
     commandbuffer::{Descriptor, ShaderView, ShaderViews},
     context::Context,
+    downcast::Downcast,
+    image::ImageHandle,
+    sampler::SamplerHandle,
 };
 
 use std::collections::HashMap;
@@ -113,11 +116,44 @@ pub trait DescriptorApi {
     fn write(&self, handle: DescriptorHandle, data: &Descriptor);
 }
 
+/// Shader stages a descriptor set layout is visible from. Drives the
+/// `stage_flags` of every binding in the layout.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+    AllGraphics,
+    /// Every graphics stage plus compute. Used for layouts shared between a
+    /// descriptor pool allocation and a pipeline layout, where both sides must
+    /// agree on `stageFlags` to stay layout-compatible (see
+    /// [`CreatePool::create_pool`] and the Vulkan pipeline layout builder).
+    All,
+}
+
+pub trait CreateLayout {
+    fn create_layout(&self, bindings: &[Binding<DescriptorType>], stage: ShaderStage)
+        -> NativeLayout;
+}
+
+pub trait LayoutApi: Downcast {
+    fn bindings(&self) -> &[Binding<DescriptorType>];
+}
+impl_downcast!(LayoutApi);
+
+pub struct NativeLayout {
+    pub inner: Box<dyn LayoutApi>,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct DescriptorSizes {
     pub buffer: u32,
     pub storage: u32,
-    pub images: u32,
+    // `SampledImage` and `CombinedImageSampler` are distinct Vulkan descriptor
+    // types (the former has no sampler bound), so each needs its own pool-size
+    // count or allocation fails with a type mismatch.
+    pub sampled_images: u32,
+    pub combined_image_samplers: u32,
 }
 
 impl DescriptorSizes {
@@ -125,12 +161,15 @@ impl DescriptorSizes {
         let sizes = DescriptorSizes {
             buffer: 0,
             storage: 0,
-            images: 0,
+            sampled_images: 0,
+            combined_image_samplers: 0,
         };
         views.iter().fold(sizes, |mut acc, elem| {
             match elem.ty {
                 DescriptorType::Uniform => acc.buffer += 1,
                 DescriptorType::Storage => acc.storage += 1,
+                DescriptorType::SampledImage => acc.sampled_images += 1,
+                DescriptorType::CombinedImageSampler => acc.combined_image_samplers += 1,
             }
             acc
         })
@@ -157,12 +196,23 @@ impl DescriptorInfo for () {
 pub enum DescriptorType {
     Uniform,
     Storage,
+    SampledImage,
+    CombinedImageSampler,
 }
 pub enum DescriptorResource {
     Uniform(BufferHandle),
     Storage(BufferHandle),
+    // No sampler: the `texture2D` case, read through a sampler declared
+    // separately in the shader.
+    SampledImage {
+        image: ImageHandle,
+    },
+    CombinedImageSampler {
+        image: ImageHandle,
+        sampler: SamplerHandle,
+    },
 }
-#[derive(Debug)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct Binding<T> {
     pub binding: u32,
     pub data: T,
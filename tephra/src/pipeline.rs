@@ -1,18 +1,125 @@
-//use renderpass::{Pass, Renderpass};
+use crate::descriptor::{Binding, DescriptorInfo, DescriptorType};
+use crate::downcast::Downcast;
 use shader::ShaderModule;
 
-// pub trait CreatePipeline {
-//     fn from_pipeline_builder(&self, pipline_builder: PipelineState) -> Pipeline;
-// }
+pub trait CreatePipeline {
+    fn from_pipeline_builder(&self, pipeline_builder: PipelineState) -> Pipeline;
+    fn from_compute_builder(&self, pipeline_builder: ComputePipelineState) -> Pipeline;
+}
+
+pub trait PipelineApi: Downcast {}
+impl_downcast!(PipelineApi);
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum PrimitiveTopology {
+    PointList,
+    LineList,
+    TriangleList,
+    TriangleStrip,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+/// Format of a single vertex attribute. Kept backend agnostic; the Vulkan
+/// backend maps each variant onto the matching `vk::Format`.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum VertexFormat {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: VertexFormat,
+    pub offset: u32,
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct VertexInputBinding {
+    pub binding: u32,
+    pub stride: u32,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct RasterizationState {
+    pub polygon_mode: PolygonMode,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+}
+
+impl Default for RasterizationState {
+    fn default() -> Self {
+        RasterizationState {
+            polygon_mode: PolygonMode::Fill,
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct DepthStencilState {
+    pub depth_test: bool,
+    pub depth_write: bool,
+}
 
-// pub trait PipelineApi: Downcast {
-// }
-// impl_downcast!(PipelineApi);
+impl Default for DepthStencilState {
+    // Off by default: the Vulkan backend's `compatible_renderpass` only
+    // declares a color attachment, so a default-on depth test would be
+    // silently ignored (no depth attachment to test against) rather than
+    // doing what the name implies. Opt in with `with_depth_stencil` once the
+    // render pass actually carries a depth attachment.
+    fn default() -> Self {
+        DepthStencilState {
+            depth_test: false,
+            depth_write: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct ColorBlendAttachment {
+    pub blend: bool,
+}
 
-#[derive(Clone)]
+impl Default for ColorBlendAttachment {
+    fn default() -> Self {
+        ColorBlendAttachment { blend: false }
+    }
+}
+
+#[derive(Clone, Hash, Eq, PartialEq)]
 pub struct PipelineState {
     pub vertex_shader: Option<ShaderModule>,
     pub fragment_shader: Option<ShaderModule>,
+    pub vertex_input: Vec<VertexInputBinding>,
+    pub topology: PrimitiveTopology,
+    pub rasterization: RasterizationState,
+    pub depth_stencil: DepthStencilState,
+    pub color_blend: Vec<ColorBlendAttachment>,
+    pub layout: Vec<Binding<DescriptorType>>,
 }
 
 impl PipelineState {
@@ -20,6 +127,12 @@ impl PipelineState {
         PipelineState {
             vertex_shader: None,
             fragment_shader: None,
+            vertex_input: Vec::new(),
+            topology: PrimitiveTopology::TriangleList,
+            rasterization: RasterizationState::default(),
+            depth_stencil: DepthStencilState::default(),
+            color_blend: Vec::new(),
+            layout: Vec::new(),
         }
     }
 
@@ -37,16 +150,96 @@ impl PipelineState {
         }
     }
 
-    // pub fn build(self, ctx: &Context) -> Pipeline {
-    //     ctx.from_pipeline_builder(self)
-    // }
+    pub fn with_vertex_input(mut self, binding: VertexInputBinding) -> Self {
+        self.vertex_input.push(binding);
+        self
+    }
+
+    pub fn with_topology(self, topology: PrimitiveTopology) -> Self {
+        PipelineState { topology, ..self }
+    }
+
+    pub fn with_rasterization(self, rasterization: RasterizationState) -> Self {
+        PipelineState {
+            rasterization,
+            ..self
+        }
+    }
+
+    pub fn with_depth_stencil(self, depth_stencil: DepthStencilState) -> Self {
+        PipelineState {
+            depth_stencil,
+            ..self
+        }
+    }
+
+    pub fn with_color_blend(mut self, attachment: ColorBlendAttachment) -> Self {
+        self.color_blend.push(attachment);
+        self
+    }
+
+    /// Derive the pipeline layout from the descriptor set bound to the
+    /// pipeline. The `vk::PipelineLayout` is built from these bindings by the
+    /// backend, reusing the same `Binding<DescriptorType>` the descriptor pool
+    /// already keys on.
+    pub fn with_descriptor<D: DescriptorInfo>(self) -> Self {
+        PipelineState {
+            layout: D::layout(),
+            ..self
+        }
+    }
+
+    pub fn build(self, ctx: &Context) -> Pipeline {
+        ctx.from_pipeline_builder(self)
+    }
+}
+
+#[derive(Clone, Hash, Eq, PartialEq)]
+pub struct ComputePipelineState {
+    pub compute_shader: Option<ShaderModule>,
+    pub layout: Vec<Binding<DescriptorType>>,
 }
 
-// pub struct Pipeline {
-//     pub data: Box<dyn PipelineApi>,
-// }
-// impl Pipeline {
-//     pub fn downcast<B: BackendApi>(&self) -> &B::Pipeline {
-//         self.data.downcast_ref::<B::Pipeline>().expect("Vulkan Backend Pipeline")
-//     }
-// }
+impl ComputePipelineState {
+    pub fn new() -> Self {
+        ComputePipelineState {
+            compute_shader: None,
+            layout: Vec::new(),
+        }
+    }
+
+    pub fn with_compute_shader(self, shader: ShaderModule) -> Self {
+        ComputePipelineState {
+            compute_shader: Some(shader),
+            ..self
+        }
+    }
+
+    pub fn with_descriptor<D: DescriptorInfo>(self) -> Self {
+        ComputePipelineState {
+            layout: D::layout(),
+            ..self
+        }
+    }
+
+    pub fn build(self, ctx: &Context) -> Pipeline {
+        ctx.from_compute_builder(self)
+    }
+}
+
+use crate::backend::BackendApi;
+use crate::context::Context;
+
+pub struct Pipeline {
+    pub data: Box<dyn PipelineApi>,
+}
+impl Pipeline {
+    pub fn downcast<B: BackendApi>(&self) -> &B::Pipeline
+    where
+        B::Pipeline: PipelineApi,
+    {
+        self.data
+            .downcast_ref::<B::Pipeline>()
+            .expect("Vulkan Backend Pipeline")
+    }
+}
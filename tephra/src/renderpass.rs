@@ -0,0 +1,162 @@
+use crate::image::{ImageHandle, ImageLayout, Resolution};
+use std::collections::HashMap;
+
+crate::new_typed_handle!(RenderpassHandle);
+crate::new_typed_handle!(FramebufferHandle);
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum LoadOp {
+    Load,
+    Clear,
+    DontCare,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum StoreOp {
+    Store,
+    DontCare,
+}
+
+/// Format of a single attachment. Backend agnostic; the Vulkan backend maps
+/// each variant onto the matching `vk::Format`.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Format {
+    Rgba8Unorm,
+    Bgra8Unorm,
+    Bgra8Srgb,
+    D32Float,
+}
+
+/// Everything about an attachment that influences the `vk::RenderPass` it
+/// belongs to: its format, how it is loaded/stored, and the layouts it
+/// transitions between across the single subpass.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct AttachmentDescription {
+    pub format: Format,
+    pub load_op: LoadOp,
+    pub store_op: StoreOp,
+    pub initial_layout: ImageLayout,
+    pub final_layout: ImageLayout,
+}
+
+/// Hashable key identifying a render pass. Render passes are cheap to keep
+/// around forever, so the cache never evicts them.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct RenderpassKey {
+    pub attachments: Vec<AttachmentDescription>,
+}
+
+/// Hashable key identifying a framebuffer. Under `VK_KHR_imageless_framebuffer`
+/// the concrete views are omitted (`views` left empty) and bound at
+/// `begin_render_pass` time instead, collapsing the N per-swapchain-image
+/// framebuffers into one.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct FramebufferKey {
+    pub renderpass: RenderpassKey,
+    pub views: Vec<ImageHandle>,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub trait RenderpassApi {
+    fn create_renderpass(&self, key: &RenderpassKey) -> RenderpassHandle;
+}
+
+pub trait FramebufferApi {
+    // `renderpass` is the handle the cache already memoized for
+    // `key.renderpass` (see `RenderpassCache::framebuffer`), so implementers
+    // must build the framebuffer against that render pass instead of building
+    // a new one from the key.
+    fn create_framebuffer(&self, key: &FramebufferKey, renderpass: RenderpassHandle)
+        -> FramebufferHandle;
+    fn destroy_framebuffer(&self, handle: FramebufferHandle);
+}
+
+/// Memoizes `RenderpassHandle`/`FramebufferHandle` objects so that recreating
+/// them every frame no longer pays the full allocation cost. Owned by the
+/// `Context`.
+///
+/// Framebuffer entries are tied to the lifetime of the views that feed them:
+/// when a contributing `Image`/`ImageView` is dropped (e.g. on
+/// `SwapchainData::recreate`) the owner calls [`RenderpassCache::invalidate_image`]
+/// so the stale framebuffer is not handed back.
+pub struct RenderpassCache {
+    renderpasses: HashMap<RenderpassKey, RenderpassHandle>,
+    framebuffers: HashMap<FramebufferKey, FramebufferHandle>,
+    imageless: bool,
+}
+
+impl RenderpassCache {
+    pub fn new(imageless: bool) -> Self {
+        RenderpassCache {
+            renderpasses: HashMap::new(),
+            framebuffers: HashMap::new(),
+            imageless,
+        }
+    }
+
+    pub fn renderpass<A>(&mut self, api: &A, key: RenderpassKey) -> RenderpassHandle
+    where
+        A: RenderpassApi,
+    {
+        *self
+            .renderpasses
+            .entry(key.clone())
+            .or_insert_with(|| api.create_renderpass(&key))
+    }
+
+    pub fn framebuffer<A>(
+        &mut self,
+        api: &A,
+        renderpass: RenderpassKey,
+        views: &[ImageHandle],
+        resolution: Resolution,
+    ) -> FramebufferHandle
+    where
+        A: FramebufferApi + RenderpassApi,
+    {
+        // Resolve (and, on first use, create) the render pass this framebuffer
+        // must be compatible with before building its key, so the handle can
+        // be threaded down to `create_framebuffer` instead of it rebuilding a
+        // second, uncached `vk::RenderPass`.
+        let renderpass_handle = self.renderpass(api, renderpass.clone());
+        let key = FramebufferKey {
+            renderpass,
+            // Imageless framebuffers do not bake the concrete views into their
+            // identity, so one framebuffer serves every swapchain image.
+            views: if self.imageless {
+                Vec::new()
+            } else {
+                views.to_vec()
+            },
+            width: resolution.width,
+            height: resolution.height,
+        };
+        *self
+            .framebuffers
+            .entry(key.clone())
+            .or_insert_with(|| api.create_framebuffer(&key, renderpass_handle))
+    }
+
+    /// Drop every framebuffer that was built from `image`. Called when the
+    /// image (or its view) is destroyed so recreation does not hand back a
+    /// dangling framebuffer. Imageless framebuffers reference no views and are
+    /// therefore untouched. Each dropped entry's `vk::Framebuffer` is destroyed
+    /// through `api` so the cache is the only owner of the object's lifetime.
+    pub fn invalidate_image<A>(&mut self, api: &A, image: ImageHandle)
+    where
+        A: FramebufferApi,
+    {
+        let stale: Vec<FramebufferKey> = self
+            .framebuffers
+            .keys()
+            .filter(|key| key.views.contains(&image))
+            .cloned()
+            .collect();
+        for key in stale {
+            if let Some(handle) = self.framebuffers.remove(&key) {
+                api.destroy_framebuffer(handle);
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::context::Context;
+
+crate::new_typed_handle!(SamplerHandle);
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum AddressMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum MipmapMode {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct SamplerDesc {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: MipmapMode,
+    pub address_mode: AddressMode,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        SamplerDesc {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_mode: MipmapMode::Linear,
+            address_mode: AddressMode::Repeat,
+        }
+    }
+}
+
+pub trait CreateSampler {
+    fn create_sampler(&self, desc: SamplerDesc) -> SamplerHandle;
+}
+
+pub struct Sampler {
+    pub ctx: Context,
+    pub handle: SamplerHandle,
+}
+
+impl Sampler {
+    pub fn new(ctx: &Context, desc: SamplerDesc) -> Self {
+        Sampler {
+            ctx: ctx.clone(),
+            handle: ctx.create_sampler(desc),
+        }
+    }
+}